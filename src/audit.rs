@@ -0,0 +1,144 @@
+//! An opt-in auditor that rejects unsafe paths produced by [`convert_path`](crate::convert_path)
+//!
+//! This ports the idea behind Mercurial's `PathAuditor`: a converted path that a caller is about
+//! to hand to a filesystem write should be checked for `..`/`.` components and for symlinks that
+//! could redirect part of the path outside the volume it was resolved against, before that write
+//! happens.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Result};
+
+/// Audits paths resolved under a single volume root, rejecting `..`/`.` components and paths
+/// that traverse a symlink partway through
+///
+/// Directories that have already been audited are cached, so repeated audits of paths that share
+/// a prefix only `stat` each intermediate directory once.
+#[derive(Debug)]
+pub struct PathAuditor {
+    volume_root: PathBuf,
+    audited: RefCell<HashSet<PathBuf>>,
+}
+
+impl PathAuditor {
+    /// Creates an auditor that only accepts paths under `volume_root`
+    pub fn new(volume_root: impl Into<PathBuf>) -> PathAuditor {
+        PathAuditor {
+            volume_root: volume_root.into(),
+            audited: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Checks that `path` stays under this auditor's volume root
+    ///
+    /// Rejects `path` if it contains a `.` or `..` component, if it is not under the volume
+    /// root at all, or if any intermediate directory between the volume root and `path` is a
+    /// symlink.
+    pub fn audit(&self, path: &Path) -> Result<()> {
+        // `Path::components` drops a `.` that is not at the start, so check the raw segments instead
+        if path
+            .to_string_lossy()
+            .split(std::path::is_separator)
+            .any(|segment| segment == "." || segment == "..")
+        {
+            return Err(Error::NotUnderVolume(path.to_owned()));
+        }
+
+        let relative = path
+            .strip_prefix(&self.volume_root)
+            .map_err(|_| Error::NotUnderVolume(path.to_owned()))?;
+
+        // Walk each directory between the volume root and `path`, re-reading symlinks at every
+        // level rather than only checking the final component.
+        let mut prefix = self.volume_root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            prefix.push(component);
+            if components.peek().is_none() {
+                // `path` itself may legitimately be a symlink; only intermediate directories
+                // are audited.
+                break;
+            }
+            if self.audited.borrow().contains(&prefix) {
+                continue;
+            }
+            match fs::symlink_metadata(&prefix) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(Error::TraversesSymbolicLink(prefix));
+                }
+                Ok(_) => {
+                    self.audited.borrow_mut().insert(prefix.clone());
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    // The path doesn't exist yet, so there is nothing further to audit.
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::os::unix::fs::symlink;
+
+    fn temp_volume(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("hfs_paths-audit-test-{}", name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_audit_accepts_plain_path() {
+        let root = temp_volume("plain");
+        fs::create_dir_all(root.join("folder1")).unwrap();
+        let auditor = PathAuditor::new(&root);
+        auditor.audit(&root.join("folder1").join("file")).unwrap();
+    }
+
+    #[test]
+    fn test_audit_rejects_dot_dot() {
+        let root = temp_volume("dotdot");
+        let auditor = PathAuditor::new(&root);
+        let escaping = root.join("folder1").join("..").join("..").join("etc").join("passwd");
+        match auditor.audit(&escaping) {
+            Err(Error::NotUnderVolume(_)) => (),
+            other => panic!("expected NotUnderVolume, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_audit_rejects_intermediate_symlink() {
+        let root = temp_volume("symlink");
+        let real_dir = root.join("real");
+        fs::create_dir_all(&real_dir).unwrap();
+        let link = root.join("link");
+        symlink(&real_dir, &link).unwrap();
+
+        let auditor = PathAuditor::new(&root);
+        match auditor.audit(&link.join("file")) {
+            Err(Error::TraversesSymbolicLink(path)) => assert_eq!(path, link),
+            other => panic!("expected TraversesSymbolicLink, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_audit_rejects_path_outside_volume() {
+        let root = temp_volume("outside");
+        let auditor = PathAuditor::new(&root);
+        match auditor.audit(Path::new("/etc/passwd")) {
+            Err(Error::NotUnderVolume(_)) => (),
+            other => panic!("expected NotUnderVolume, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! A validated HFS path type, modeled on Mercurial's `HgPath`/`HgPathBuf`
+//!
+//! `HfsPath` and `HfsPathBuf` wrap raw bytes rather than `&str`/`String` so that validation can
+//! happen once, at construction, instead of being re-checked (or silently skipped) by every
+//! function that takes a path. A path is rejected if it contains an embedded null byte, an empty
+//! volume name, or an empty segment created by a `::` separator.
+
+use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::{Error, Result};
+
+/// A borrowed, validated HFS path
+#[repr(transparent)]
+pub struct HfsPath {
+    bytes: [u8],
+}
+
+impl HfsPath {
+    /// Wraps `bytes` as an `HfsPath` without validating them
+    fn from_bytes_unchecked(bytes: &[u8]) -> &HfsPath {
+        // Safe because `HfsPath` is `#[repr(transparent)]` over `[u8]`
+        unsafe { &*(bytes as *const [u8] as *const HfsPath) }
+    }
+
+    /// Validates `bytes` and wraps them as an `HfsPath`
+    pub fn parse(bytes: &[u8]) -> Result<&HfsPath> {
+        validate(bytes)?;
+        Ok(HfsPath::from_bytes_unchecked(bytes))
+    }
+
+    /// Returns the path's raw bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the path as a `str`, if it is valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.bytes).ok()
+    }
+}
+
+impl fmt::Debug for HfsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&String::from_utf8_lossy(&self.bytes), f)
+    }
+}
+
+impl PartialEq for HfsPath {
+    fn eq(&self, other: &HfsPath) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for HfsPath {}
+
+impl Hash for HfsPath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state)
+    }
+}
+
+impl AsRef<HfsPath> for HfsPath {
+    fn as_ref(&self) -> &HfsPath {
+        self
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for &'a HfsPath {
+    type Error = Error;
+
+    fn try_from(bytes: &'a [u8]) -> Result<&'a HfsPath> {
+        HfsPath::parse(bytes)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a HfsPath {
+    type Error = Error;
+
+    fn try_from(s: &'a str) -> Result<&'a HfsPath> {
+        HfsPath::parse(s.as_bytes())
+    }
+}
+
+impl ToOwned for HfsPath {
+    type Owned = HfsPathBuf;
+
+    fn to_owned(&self) -> HfsPathBuf {
+        HfsPathBuf { bytes: self.bytes.to_vec() }
+    }
+}
+
+/// An owned, validated HFS path
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HfsPathBuf {
+    bytes: Vec<u8>,
+}
+
+impl HfsPathBuf {
+    /// Validates `bytes` and wraps them as an `HfsPathBuf`
+    pub fn parse(bytes: Vec<u8>) -> Result<HfsPathBuf> {
+        validate(&bytes)?;
+        Ok(HfsPathBuf { bytes })
+    }
+}
+
+impl Deref for HfsPathBuf {
+    type Target = HfsPath;
+
+    fn deref(&self) -> &HfsPath {
+        HfsPath::from_bytes_unchecked(&self.bytes)
+    }
+}
+
+impl Borrow<HfsPath> for HfsPathBuf {
+    fn borrow(&self) -> &HfsPath {
+        self
+    }
+}
+
+impl AsRef<HfsPath> for HfsPathBuf {
+    fn as_ref(&self) -> &HfsPath {
+        self
+    }
+}
+
+impl TryFrom<Vec<u8>> for HfsPathBuf {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<HfsPathBuf> {
+        HfsPathBuf::parse(bytes)
+    }
+}
+
+impl TryFrom<String> for HfsPathBuf {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<HfsPathBuf> {
+        HfsPathBuf::parse(s.into_bytes())
+    }
+}
+
+impl TryFrom<&str> for HfsPathBuf {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<HfsPathBuf> {
+        HfsPathBuf::parse(s.as_bytes().to_vec())
+    }
+}
+
+/// Checks `bytes` for an embedded null byte, an empty volume name, and empty segments created by
+/// a `::` separator
+///
+/// Shared with [`crate::os_path`], so the byte-oriented conversion functions reject the same
+/// malformed paths as [`HfsPath::parse`] instead of silently collapsing empty segments.
+pub(crate) fn validate(bytes: &[u8]) -> Result<()> {
+    if let Some(offset) = bytes.iter().position(|&b| b == 0) {
+        return Err(Error::NullByteInPath(offset));
+    }
+
+    let mut offset = 0;
+    for (index, segment) in bytes.split(|&b| b == b':').enumerate() {
+        if segment.is_empty() {
+            if index == 0 {
+                return Err(Error::EmptyVolumeName);
+            }
+            return Err(Error::EmptyPathSegment(offset));
+        }
+        offset += segment.len() + 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid() {
+        assert!(HfsPath::parse(b"Macintosh SSD:folder1:file").is_ok());
+        assert!(HfsPath::parse(b"Macintosh SSD").is_ok());
+    }
+
+    #[test]
+    fn test_parse_null_byte() {
+        match HfsPath::parse(b"Macintosh SSD:folder\x001:file") {
+            Err(Error::NullByteInPath(offset)) => assert_eq!(offset, 20),
+            other => panic!("expected NullByteInPath, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_empty_volume_name() {
+        match HfsPath::parse(b":folder1:file") {
+            Err(Error::EmptyVolumeName) => (),
+            other => panic!("expected EmptyVolumeName, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_consecutive_separators() {
+        match HfsPath::parse(b"Macintosh SSD::file") {
+            Err(Error::EmptyPathSegment(offset)) => assert_eq!(offset, 14),
+            other => panic!("expected EmptyPathSegment, got {:?}", other),
+        }
+    }
+}
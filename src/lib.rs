@@ -9,13 +9,42 @@
 #[macro_use]
 extern crate quick_error;
 
-use std::ffi::OsStr;
+mod audit;
+mod hfs_path;
+#[cfg(unix)]
+mod os_path;
+mod url;
+mod volume;
+
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+
+pub use crate::audit::PathAuditor;
+pub use crate::hfs_path::{HfsPath, HfsPathBuf};
+#[cfg(unix)]
+pub use crate::os_path::{
+    convert_path_bytes, convert_path_bytes_with, convert_path_os, convert_path_os_with, MapOsVolumeResolver,
+    OsVolumeResolver,
+};
+pub use crate::url::{file_url_to_path, file_url_to_path_with, hfs_to_file_url, hfs_to_file_url_with};
+pub use crate::volume::{MapVolumeResolver, SystemVolumeResolver, VolumeResolver};
+
+/// Converts the provided HFS path into a standard POSIX path, resolving volumes via
+/// `SystemVolumeResolver`
+///
+/// See [`convert_path_with`] to use a different [`VolumeResolver`], e.g. in tests.
+pub fn convert_path(path: impl AsRef<HfsPath>) -> Result<PathBuf> {
+    convert_path_with(path, &SystemVolumeResolver)
+}
 
-/// Converts the provided HFS path into a standard POSIX path
-pub fn convert_path(path: &str) -> Result<PathBuf> {
+/// Converts the provided HFS path into a standard POSIX path, resolving volumes with `resolver`
+pub fn convert_path_with<R: VolumeResolver + ?Sized>(
+    path: impl AsRef<HfsPath>,
+    resolver: &R,
+) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let path = path.as_str().ok_or(Error::InvalidHfsPath)?;
     // : is the directory separator
     let mut segments = path.split(':');
     // Check for the volume name as the first path segment
@@ -24,7 +53,7 @@ pub fn convert_path(path: &str) -> Result<PathBuf> {
             // Replace slashes with colons
             let volume_name = volume_name.replace('/', ":");
             // Find the POSIX path to this volume
-            let mut path = find_volume(&volume_name)?;
+            let mut path = resolver.resolve(&volume_name)?;
             // Append other path segments and separators
             for segment in segments {
                 let segment = segment.replace('/', ":");
@@ -36,21 +65,83 @@ pub fn convert_path(path: &str) -> Result<PathBuf> {
     }
 }
 
-/// Looks for a volume with the provided name and returns the absolute path to its root
-fn find_volume(name: &str) -> Result<PathBuf> {
-    for entry in fs::read_dir("/Volumes")? {
-        let entry = entry?;
-        if entry.file_name() == OsStr::new(name) {
-            if entry.file_type()?.is_symlink() {
-                // Follow link
-                let link_dest = fs::read_link(entry.path())?;
-                return Ok(link_dest)
-            } else {
-                return Ok(entry.path())
+/// Converts the provided absolute POSIX path into an HFS path, resolving volumes via
+/// `SystemVolumeResolver`
+///
+/// See [`convert_to_hfs_with`] to use a different [`VolumeResolver`], e.g. in tests.
+pub fn convert_to_hfs(path: &Path) -> Result<String> {
+    convert_to_hfs_with(path, &SystemVolumeResolver)
+}
+
+/// Converts the provided absolute POSIX path into an HFS path, resolving volumes with `resolver`
+///
+/// The volume that contains `path` is found by comparing `path` against the root of every volume
+/// `resolver` knows about; the volume whose root is the longest prefix of `path` wins. There is
+/// no dedicated lookup for the startup volume (the one backing `/`) — `resolver` must report an
+/// entry whose root canonicalizes to `/` itself, the same way it reports any other volume, or a
+/// path under `/` can't be resolved to a real volume name. [`SystemVolumeResolver`] gets this for
+/// free only if `/Volumes` happens to contain a symlink back to the startup disk; if `path` is
+/// under `/` and no volume's root matches `/` exactly, this returns
+/// [`Error::StartupVolumeNotFound`] rather than silently misattributing the path to some other
+/// volume.
+///
+/// `path` must already be normalized: a `.` or `..` component is rejected with
+/// [`Error::NonNormalizedPath`] rather than resolved away, since doing so silently would change
+/// which file the returned HFS path refers to (e.g. `/Volumes/BOOTCAMP/Intel/../etc/passwd`
+/// would otherwise convert as if it were `/Volumes/BOOTCAMP/Intel/etc/passwd`). Call
+/// `path.canonicalize()` first if `path` may contain either.
+pub fn convert_to_hfs_with<R: VolumeResolver + ?Sized>(path: &Path, resolver: &R) -> Result<String> {
+    if !path.is_absolute() {
+        return Err(Error::InvalidPosixPath);
+    }
+    // `Path::components` drops `.` from an absolute path, so check the raw segments instead
+    if path
+        .to_string_lossy()
+        .split(std::path::is_separator)
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return Err(Error::NonNormalizedPath(path.to_owned()));
+    }
+    let (volume_name, volume_root) = find_enclosing_volume(path, resolver)?;
+    let relative = path
+        .strip_prefix(&volume_root)
+        .expect("volume_root was already checked to be a prefix of path");
+    let mut hfs_path = volume_name;
+    for component in relative.components() {
+        if let Component::Normal(segment) = component {
+            // A literal `:` in a POSIX component came from a literal `/` in the HFS component
+            hfs_path.push(':');
+            hfs_path.push_str(&segment.to_string_lossy().replace(':', "/"));
+        }
+    }
+    Ok(hfs_path)
+}
+
+/// Finds the volume known to `resolver` whose root is the longest prefix of `path`
+fn find_enclosing_volume<R: VolumeResolver + ?Sized>(
+    path: &Path,
+    resolver: &R,
+) -> Result<(String, PathBuf)> {
+    let startup_root = fs::canonicalize("/")?;
+    let mut found_startup = false;
+    let mut best: Option<(String, PathBuf)> = None;
+    for (name, root) in resolver.volumes()? {
+        found_startup |= root == startup_root;
+        if path.starts_with(&root) {
+            let is_longer_match = match &best {
+                Some((_, best_root)) => root.as_os_str().len() > best_root.as_os_str().len(),
+                None => true,
+            };
+            if is_longer_match {
+                best = Some((name, root));
             }
         }
     }
-    Err(Error::VolumeNotFound(name.into()))
+    match best {
+        Some(found) => Ok(found),
+        None if !found_startup => Err(Error::StartupVolumeNotFound),
+        None => Err(Error::NoVolumeForPath(path.to_owned())),
+    }
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -64,11 +155,66 @@ quick_error! {
             description("invalid HFS path format")
             display("Invalid HFS path format")
         }
+        /// A POSIX path that was not absolute was provided where an absolute path was required
+        InvalidPosixPath {
+            description("invalid POSIX path")
+            display("POSIX path is not absolute")
+        }
         /// A mounted volume with the specified name was not found
         VolumeNotFound(volume: String) {
             description("volume not found")
             display("Volume {} not found", volume)
         }
+        /// No mounted volume contains the specified POSIX path
+        NoVolumeForPath(path: PathBuf) {
+            description("no volume contains this path")
+            display("No mounted volume contains the path {}", path.display())
+        }
+        /// A POSIX path given to [`convert_to_hfs_with`](crate::convert_to_hfs_with) contained a
+        /// `.` or `..` component, which would silently change which file the resulting HFS path
+        /// refers to
+        NonNormalizedPath(path: PathBuf) {
+            description("path is not normalized")
+            display("POSIX path {} contains a `.` or `..` component and must be canonicalized first", path.display())
+        }
+        /// A path under `/` was given, but no known volume's root resolved to `/` itself, so the
+        /// startup volume's real name could not be determined
+        StartupVolumeNotFound {
+            description("startup volume not found")
+            display("No volume resolves to the startup disk's root")
+        }
+        /// A `file://` URL with an invalid format was provided
+        InvalidFileUrl {
+            description("invalid file:// URL format")
+            display("Invalid file:// URL format")
+        }
+        /// An HFS path contained an embedded null byte at the given offset
+        NullByteInPath(offset: usize) {
+            description("null byte in path")
+            display("Null byte in path at byte offset {}", offset)
+        }
+        /// An HFS path had an empty volume name
+        EmptyVolumeName {
+            description("empty volume name")
+            display("HFS path has an empty volume name")
+        }
+        /// An HFS path contained a `::` separator, which would create an empty path segment
+        EmptyPathSegment(offset: usize) {
+            description("empty path segment")
+            display("HFS path has an empty segment at byte offset {}", offset)
+        }
+        /// A [`PathAuditor`](crate::PathAuditor) found a symlink partway through a path, rather
+        /// than only at its final component
+        TraversesSymbolicLink(path: PathBuf) {
+            description("path traverses a symbolic link")
+            display("Path {} traverses a symbolic link", path.display())
+        }
+        /// A [`PathAuditor`](crate::PathAuditor) rejected a path that was not under its volume
+        /// root, or that contained a `.`/`..` component
+        NotUnderVolume(path: PathBuf) {
+            description("path is not safely under the volume root")
+            display("Path {} is not safely under the volume root", path.display())
+        }
         /// An IO error occurred
         Io(err: io::Error) {
             description("I/O error")
@@ -89,10 +235,16 @@ mod tests {
         { } => { [("", ""); 0] };
     }
 
+    fn test_resolver() -> MapVolumeResolver {
+        let mut resolver = MapVolumeResolver::default();
+        resolver.0.insert("Macintosh SSD".into(), PathBuf::from("/"));
+        resolver.0.insert("BOOTCAMP".into(), PathBuf::from("/Volumes/BOOTCAMP"));
+        resolver
+    }
+
     #[test]
     fn test_paths() {
-        // Note: These tests depend on the layout of volumes on the computer that they run on.
-        // The volumes must be present for the tests to pass.
+        let resolver = test_resolver();
         let tests = expect! {
             "Macintosh SSD:folder1:file" => "/folder1/file",
             "Macintosh SSD" => "/",
@@ -100,9 +252,48 @@ mod tests {
             "BOOTCAMP:Intel:Logs:IntelGFX.log" => "/Volumes/BOOTCAMP/Intel/Logs/IntelGFX.log"
         };
 
-        for &(hfs, expected) in tests.into_iter() {
-            let actual = convert_path(hfs).unwrap();
+        for &(hfs, expected) in tests.iter() {
+            let hfs = HfsPath::parse(hfs.as_bytes()).unwrap();
+            let actual = convert_path_with(hfs, &resolver).unwrap();
             assert_eq!(expected, &actual.display().to_string());
         }
     }
+
+    #[test]
+    fn test_paths_reverse() {
+        let resolver = test_resolver();
+        let tests = expect! {
+            "/folder1/file" => "Macintosh SSD:folder1:file",
+            "/" => "Macintosh SSD",
+            "/folder:with:slashes/file.txt" => "Macintosh SSD:folder/with/slashes:file.txt",
+            "/Volumes/BOOTCAMP/Intel/Logs/IntelGFX.log" => "BOOTCAMP:Intel:Logs:IntelGFX.log"
+        };
+
+        for &(posix, expected) in tests.iter() {
+            let actual = convert_to_hfs_with(Path::new(posix), &resolver).unwrap();
+            assert_eq!(expected, &actual);
+        }
+    }
+
+    #[test]
+    fn test_paths_reverse_startup_volume_not_found() {
+        // No volume in this resolver resolves to `/`
+        let mut resolver = MapVolumeResolver::default();
+        resolver.0.insert("BOOTCAMP".into(), PathBuf::from("/Volumes/BOOTCAMP"));
+
+        match convert_to_hfs_with(Path::new("/"), &resolver) {
+            Err(Error::StartupVolumeNotFound) => (),
+            other => panic!("expected StartupVolumeNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_paths_reverse_rejects_non_normalized_path() {
+        let resolver = test_resolver();
+        let path = Path::new("/Volumes/BOOTCAMP/Intel/../etc/passwd");
+        match convert_to_hfs_with(path, &resolver) {
+            Err(Error::NonNormalizedPath(rejected)) => assert_eq!(rejected, path),
+            other => panic!("expected NonNormalizedPath, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,183 @@
+//! Byte- and `OsStr`-oriented path conversion
+//!
+//! `convert_path` only accepts HFS paths that are valid UTF-8, returning `Error::InvalidHfsPath`
+//! for volume names and components that aren't. `convert_path_os`/`convert_path_bytes` split and
+//! remap the `:`/`/` separators directly on bytes, following the approach in Mercurial's
+//! `files.rs` (`get_path_from_bytes`/`get_bytes_from_os_str`), and compare raw `OsStr` file names
+//! when resolving volumes, so no volume name or path component is ever decoded.
+//!
+//! [`VolumeResolver`](crate::VolumeResolver) can't represent a raw, possibly non-UTF-8 volume
+//! name, so volume resolution here goes through the sibling [`OsVolumeResolver`] trait instead.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use crate::hfs_path::validate;
+use crate::volume::resolve_entry_root;
+use crate::{Error, MapVolumeResolver, Result, SystemVolumeResolver};
+
+/// Resolves an HFS volume name, given as raw bytes, to the absolute POSIX path of its root
+///
+/// This is the byte-oriented sibling of [`VolumeResolver`](crate::VolumeResolver), for volume
+/// names that may not be valid UTF-8.
+pub trait OsVolumeResolver {
+    /// Looks for a volume with the provided raw name and returns the absolute path to its root
+    fn resolve_os(&self, name: &OsStr) -> Result<PathBuf>;
+}
+
+impl OsVolumeResolver for SystemVolumeResolver {
+    fn resolve_os(&self, name: &OsStr) -> Result<PathBuf> {
+        for entry in fs::read_dir("/Volumes")? {
+            let entry = entry?;
+            if entry.file_name() == name {
+                return resolve_entry_root(&entry);
+            }
+        }
+        Err(Error::VolumeNotFound(name.to_string_lossy().into_owned()))
+    }
+}
+
+impl OsVolumeResolver for MapVolumeResolver {
+    fn resolve_os(&self, name: &OsStr) -> Result<PathBuf> {
+        self.0
+            .iter()
+            .find(|(key, _)| OsStr::new(key.as_str()) == name)
+            .map(|(_, root)| root.clone())
+            .ok_or_else(|| Error::VolumeNotFound(name.to_string_lossy().into_owned()))
+    }
+}
+
+/// An `OsVolumeResolver` backed by a fixed, raw volume-name-to-root mapping, for deterministic
+/// tests involving non-UTF-8 volume names
+#[derive(Debug, Default, Clone)]
+pub struct MapOsVolumeResolver(pub HashMap<Vec<u8>, PathBuf>);
+
+impl OsVolumeResolver for MapOsVolumeResolver {
+    fn resolve_os(&self, name: &OsStr) -> Result<PathBuf> {
+        self.0
+            .get(name.as_bytes())
+            .cloned()
+            .ok_or_else(|| Error::VolumeNotFound(name.to_string_lossy().into_owned()))
+    }
+}
+
+/// Converts the provided HFS path, given as an `OsStr`, into a standard POSIX path, resolving
+/// volumes via `SystemVolumeResolver`
+pub fn convert_path_os(path: &OsStr) -> Result<PathBuf> {
+    convert_path_os_with(path, &SystemVolumeResolver)
+}
+
+/// Converts the provided HFS path, given as an `OsStr`, into a standard POSIX path, resolving
+/// volumes with `resolver`
+pub fn convert_path_os_with<R: OsVolumeResolver + ?Sized>(path: &OsStr, resolver: &R) -> Result<PathBuf> {
+    convert_path_bytes_with(path.as_bytes(), resolver)
+}
+
+/// Converts the provided HFS path, given as raw bytes, into a standard POSIX path, resolving
+/// volumes via `SystemVolumeResolver`
+pub fn convert_path_bytes(path: &[u8]) -> Result<PathBuf> {
+    convert_path_bytes_with(path, &SystemVolumeResolver)
+}
+
+/// Converts the provided HFS path, given as raw bytes, into a standard POSIX path, resolving
+/// volumes with `resolver`
+pub fn convert_path_bytes_with<R: OsVolumeResolver + ?Sized>(path: &[u8], resolver: &R) -> Result<PathBuf> {
+    // Reject embedded null bytes, an empty volume name, and empty segments from a `::`
+    // separator, same as `HfsPath::parse`
+    validate(path)?;
+    // : is the directory separator
+    let mut segments = path.split(|&b| b == b':');
+    // Check for the volume name as the first path segment
+    match segments.next() {
+        Some(volume_name) => {
+            // Replace slashes with colons
+            let volume_name = remap_component(volume_name);
+            // Find the POSIX path to this volume
+            let mut path = resolver.resolve_os(OsStr::from_bytes(&volume_name))?;
+            // Append other path segments and separators
+            for segment in segments {
+                path.push(OsStr::from_bytes(&remap_component(segment)));
+            }
+            Ok(path)
+        }
+        None => Err(Error::InvalidHfsPath),
+    }
+}
+
+/// Replaces a literal `/` byte with `:`, mirroring [`convert_path`](crate::convert_path)'s
+/// string-level remapping
+fn remap_component(segment: &[u8]) -> Vec<u8> {
+    segment.iter().map(|&b| if b == b'/' { b':' } else { b }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_resolver() -> MapOsVolumeResolver {
+        let mut resolver = MapOsVolumeResolver::default();
+        resolver.0.insert(b"Macintosh SSD".to_vec(), PathBuf::from("/"));
+        resolver.0.insert(b"BOOTCAMP".to_vec(), PathBuf::from("/Volumes/BOOTCAMP"));
+        resolver
+    }
+
+    #[test]
+    fn test_convert_path_bytes() {
+        let resolver = test_resolver();
+        assert_eq!(
+            convert_path_bytes_with(b"Macintosh SSD:folder1:file", &resolver).unwrap(),
+            PathBuf::from("/folder1/file")
+        );
+        assert_eq!(
+            convert_path_bytes_with(b"Macintosh SSD:folder/with/slashes:file.txt", &resolver).unwrap(),
+            PathBuf::from("/folder:with:slashes/file.txt")
+        );
+        assert_eq!(
+            convert_path_bytes_with(b"BOOTCAMP:Intel:Logs:IntelGFX.log", &resolver).unwrap(),
+            PathBuf::from("/Volumes/BOOTCAMP/Intel/Logs/IntelGFX.log")
+        );
+    }
+
+    #[test]
+    fn test_convert_path_os() {
+        let resolver = test_resolver();
+        assert_eq!(
+            convert_path_os_with(OsStr::new("Macintosh SSD:folder1:file"), &resolver).unwrap(),
+            PathBuf::from("/folder1/file")
+        );
+    }
+
+    #[test]
+    fn test_convert_path_bytes_rejects_consecutive_separators() {
+        let resolver = test_resolver();
+        match convert_path_bytes_with(b"Macintosh SSD::folder:file", &resolver) {
+            Err(Error::EmptyPathSegment(offset)) => assert_eq!(offset, 14),
+            other => panic!("expected EmptyPathSegment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_system_resolver_canonicalizes_symlinked_volume() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join("hfs_paths-os_path-test-symlink");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let real_dir = root.join("real");
+        fs::create_dir_all(&real_dir).unwrap();
+        let volumes = root.join("Volumes");
+        fs::create_dir_all(&volumes).unwrap();
+        let link = volumes.join("LinkedVolume");
+        symlink(&real_dir, &link).unwrap();
+
+        let entry = fs::read_dir(&volumes)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .find(|entry| entry.file_name() == OsStr::new("LinkedVolume"))
+            .unwrap();
+        assert_eq!(resolve_entry_root(&entry).unwrap(), fs::canonicalize(&real_dir).unwrap());
+    }
+}
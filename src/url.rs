@@ -0,0 +1,192 @@
+//! Conversion between HFS paths, POSIX paths and `file://` URLs
+//!
+//! This mirrors the macOS-specific URL handling in Firefox's `nsURLHelperOSX`: the startup
+//! volume is omitted from the URL entirely, while every other volume appears as the first path
+//! segment after the `file://` authority.
+
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Result, SystemVolumeResolver, VolumeResolver};
+
+/// Converts the provided HFS path into a `file://` URL, resolving volumes via
+/// `SystemVolumeResolver`
+///
+/// See [`hfs_to_file_url_with`] to use a different [`VolumeResolver`], e.g. in tests.
+pub fn hfs_to_file_url(path: &str) -> Result<String> {
+    hfs_to_file_url_with(path, &SystemVolumeResolver)
+}
+
+/// Converts the provided HFS path into a `file://` URL, resolving volumes with `resolver`
+///
+/// Each path segment is percent-encoded on its own, so a literal `/` inside a segment (which, as
+/// in [`convert_path`](crate::convert_path), represents a literal `:` in the real file name) is
+/// escaped rather than treated as a URL path separator. The startup volume is omitted from the
+/// URL, matching the way Cocoa/Carbon APIs hand these URLs back.
+pub fn hfs_to_file_url_with<R: VolumeResolver + ?Sized>(path: &str, resolver: &R) -> Result<String> {
+    let mut segments = path.split(':');
+    let volume_name = segments.next().ok_or(Error::InvalidHfsPath)?;
+
+    let mut url = String::from("file://");
+    if resolver.resolve(volume_name)? != Path::new("/") {
+        url.push('/');
+        url.push_str(&percent_encode(volume_name));
+    }
+    for segment in segments {
+        url.push('/');
+        url.push_str(&percent_encode(segment));
+    }
+    if url == "file://" {
+        // The path was just the startup volume's name, i.e. the root
+        url.push('/');
+    }
+    Ok(url)
+}
+
+/// Converts the provided `file://` URL into a standard POSIX path, resolving volumes via
+/// `SystemVolumeResolver`
+///
+/// See [`file_url_to_path_with`] to use a different [`VolumeResolver`], e.g. in tests.
+pub fn file_url_to_path(url: &str) -> Result<PathBuf> {
+    file_url_to_path_with(url, &SystemVolumeResolver)
+}
+
+/// Converts the provided `file://` URL into a standard POSIX path, resolving volumes with
+/// `resolver`
+///
+/// If the URL's first path segment names a volume known to `resolver`, the URL is treated as
+/// volume-relative; otherwise the URL is assumed to already be an absolute POSIX path on the
+/// startup disk. Each decoded segment is pushed as a single path component, and is remapped the
+/// same way [`convert_path`](crate::convert_path) remaps HFS segments (a literal `/`, which
+/// [`hfs_to_file_url_with`] escaped as `%2F` to preserve a literal `:` in the real file name, is
+/// turned back into `:`) so a segment's own separators can never be reinterpreted as path
+/// separators.
+pub fn file_url_to_path_with<R: VolumeResolver + ?Sized>(url: &str, resolver: &R) -> Result<PathBuf> {
+    let rest = url.strip_prefix("file://").ok_or(Error::InvalidFileUrl)?;
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    if rest.is_empty() {
+        return Ok(PathBuf::from("/"));
+    }
+
+    let mut segments = rest.split('/');
+    let first = decode_component(segments.next().expect("split always yields at least one segment"))?;
+
+    let mut path = match resolver.resolve(&first) {
+        Ok(root) => root,
+        Err(Error::VolumeNotFound(_)) => PathBuf::from("/").join(first),
+        Err(err) => return Err(err),
+    };
+    for segment in segments {
+        path.push(decode_component(segment)?);
+    }
+    Ok(path)
+}
+
+/// Percent-decodes a URL path segment and remaps a literal `/` to `:`, the same remapping
+/// [`convert_path`](crate::convert_path) applies to HFS segments
+fn decode_component(segment: &str) -> Result<String> {
+    Ok(percent_decode(segment)?.replace('/', ":"))
+}
+
+/// Percent-encodes a single path segment, escaping `/`, spaces and non-ASCII bytes
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for &byte in segment.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Percent-decodes a single path segment
+fn percent_decode(segment: &str) -> Result<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(Error::InvalidFileUrl)?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| Error::InvalidFileUrl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapVolumeResolver;
+
+    fn test_resolver() -> MapVolumeResolver {
+        let mut resolver = MapVolumeResolver::default();
+        resolver.0.insert("Macintosh SSD".into(), PathBuf::from("/"));
+        resolver.0.insert("BOOTCAMP".into(), PathBuf::from("/Volumes/BOOTCAMP"));
+        resolver
+    }
+
+    #[test]
+    fn test_hfs_to_file_url() {
+        let resolver = test_resolver();
+        assert_eq!(
+            hfs_to_file_url_with("Macintosh SSD:folder1:file", &resolver).unwrap(),
+            "file:///folder1/file"
+        );
+        assert_eq!(
+            hfs_to_file_url_with("BOOTCAMP:Intel:Logs:IntelGFX.log", &resolver).unwrap(),
+            "file:///BOOTCAMP/Intel/Logs/IntelGFX.log"
+        );
+        assert_eq!(
+            hfs_to_file_url_with("Macintosh SSD:folder/with/slashes:file.txt", &resolver).unwrap(),
+            "file:///folder%2Fwith%2Fslashes/file.txt"
+        );
+    }
+
+    #[test]
+    fn test_file_url_to_path() {
+        let resolver = test_resolver();
+        assert_eq!(
+            file_url_to_path_with("file:///folder1/file", &resolver).unwrap(),
+            PathBuf::from("/folder1/file")
+        );
+        assert_eq!(
+            file_url_to_path_with("file:///BOOTCAMP/Intel/Logs/IntelGFX.log", &resolver).unwrap(),
+            PathBuf::from("/Volumes/BOOTCAMP/Intel/Logs/IntelGFX.log")
+        );
+    }
+
+    #[test]
+    fn test_file_url_to_path_preserves_literal_slash_in_component() {
+        // The %2F here came from a literal `/` in the original HFS component, which must round-
+        // trip back to a single path component containing `:`, not be re-split on `/`.
+        let resolver = test_resolver();
+        assert_eq!(
+            file_url_to_path_with("file:///folder%2Fwith%2Fslashes/file.txt", &resolver).unwrap(),
+            PathBuf::from("/folder:with:slashes/file.txt")
+        );
+    }
+
+    #[test]
+    fn test_round_trip_literal_slash_in_component() {
+        let resolver = test_resolver();
+        let url = hfs_to_file_url_with("Macintosh SSD:folder/with/slashes:file.txt", &resolver).unwrap();
+        let path = file_url_to_path_with(&url, &resolver).unwrap();
+        assert_eq!(path, PathBuf::from("/folder:with:slashes/file.txt"));
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("folder/with spaces"), "folder%2Fwith%20spaces");
+        assert_eq!(percent_decode("folder%2Fwith%20spaces").unwrap(), "folder/with spaces");
+    }
+}
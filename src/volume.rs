@@ -0,0 +1,81 @@
+//! Pluggable volume resolution
+//!
+//! Resolving an HFS volume name to a POSIX root directory means scanning `/Volumes`, which makes
+//! code built on it depend on the layout of volumes on the machine it runs on. `VolumeResolver`
+//! abstracts that lookup, mirroring Mercurial's `Vfs` abstraction, so library users (and tests)
+//! can supply a fixed, deterministic mapping instead.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{Error, Result};
+
+/// Resolves HFS volume names to the absolute POSIX paths of their roots
+pub trait VolumeResolver {
+    /// Looks for a volume with the provided name and returns the absolute path to its root
+    fn resolve(&self, name: &str) -> Result<PathBuf>;
+
+    /// Lists every known volume as a `(name, root)` pair
+    ///
+    /// This is used for the reverse direction, converting a POSIX path back into an HFS path, so
+    /// it has to enumerate every volume rather than look one up by name.
+    fn volumes(&self) -> Result<Vec<(String, PathBuf)>>;
+}
+
+/// The default `VolumeResolver`, which scans `/Volumes` for a matching entry
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemVolumeResolver;
+
+impl VolumeResolver for SystemVolumeResolver {
+    fn resolve(&self, name: &str) -> Result<PathBuf> {
+        for entry in fs::read_dir("/Volumes")? {
+            let entry = entry?;
+            if entry.file_name() == OsStr::new(name) {
+                return resolve_entry_root(&entry);
+            }
+        }
+        Err(Error::VolumeNotFound(name.into()))
+    }
+
+    fn volumes(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut volumes = Vec::new();
+        for entry in fs::read_dir("/Volumes")? {
+            let entry = entry?;
+            let root = resolve_entry_root(&entry)?;
+            volumes.push((entry.file_name().to_string_lossy().into_owned(), root));
+        }
+        Ok(volumes)
+    }
+}
+
+/// Resolves the real, canonical root of a `/Volumes` entry, following symlinks
+///
+/// Shared with [`crate::os_path`]'s `SystemVolumeResolver` impl, so both the `str` and
+/// byte-oriented resolvers canonicalize symlinked volumes the same way.
+pub(crate) fn resolve_entry_root(entry: &fs::DirEntry) -> Result<PathBuf> {
+    if entry.file_type()?.is_symlink() {
+        Ok(fs::canonicalize(entry.path())?)
+    } else {
+        Ok(entry.path())
+    }
+}
+
+/// A `VolumeResolver` backed by a fixed volume-name-to-root mapping, for deterministic tests or
+/// use on machines where volumes are not mounted under `/Volumes`
+#[derive(Debug, Default, Clone)]
+pub struct MapVolumeResolver(pub HashMap<String, PathBuf>);
+
+impl VolumeResolver for MapVolumeResolver {
+    fn resolve(&self, name: &str) -> Result<PathBuf> {
+        self.0
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::VolumeNotFound(name.to_owned()))
+    }
+
+    fn volumes(&self) -> Result<Vec<(String, PathBuf)>> {
+        Ok(self.0.iter().map(|(name, root)| (name.clone(), root.clone())).collect())
+    }
+}